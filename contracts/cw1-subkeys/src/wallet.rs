@@ -1,83 +1,265 @@
 use schemars::JsonSchema;
-use serde::{de, ser, Deserialize, Deserializer, Serialize};
-use std::convert::TryFrom;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::str::FromStr;
 use std::{fmt, ops};
 
-use cosmwasm_std::{underflow, StdError, Coin};
+use cosmwasm_std::{Coin, OverflowError, OverflowOperation, StdError, StdResult, Uint128};
 
-// Wallet wraps Vec<Coin> and provides some nice helpers. It mutates the Vec and can be
-// unwrapped when done.
-#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
-pub struct Wallet(pub Vec<Coin>);
+// Wallet wraps a denom -> amount map and provides some nice helpers. Keeping the coins in a
+// BTreeMap keyed by denom means the contents are always sorted, never contain a duplicate denom
+// or a zero amount, and `has`/add/subtract are O(log n) instead of scanning a Vec - there is no
+// `normalize` step to remember to call.
+#[derive(Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct Wallet(#[schemars(with = "Vec<Coin>")] BTreeMap<String, Uint128>);
 
 impl Wallet {
+    /// returns the coins as a `Vec<Coin>`, sorted by denom, for wire compatibility
     pub fn into_vec(self) -> Vec<Coin> {
         self.0
+            .into_iter()
+            .map(|(denom, amount)| Coin { denom, amount })
+            .collect()
     }
 
-    /// returns true if the list of coins has at least the required amount
+    /// returns true if the wallet has at least the required amount of the given denom
     pub fn has(&self, required: &Coin) -> bool {
         self.0
-            .iter()
-            .find(|c| c.denom == required.denom)
-            .map(|m| m.amount >= required.amount)
+            .get(&required.denom)
+            .map(|amount| *amount >= required.amount)
             .unwrap_or(false)
     }
 
-    /// normalize Wallet (sorted by denom, no 0 elements, no duplicate denoms)
-    pub fn normalize(&mut self) {
-        // drop 0's
-        self.0.retain(|c| c.amount.u128() != 0);
-        // sort
-        self.0.sort_unstable_by(|a, b| a.denom.cmp(&b.denom));
-
-        // find all i where (self[i-1].denom == self[i].denom).
-        let mut dups: Vec<usize> = self
+    /// adds `coin` in place, taking it by reference for use in tight loops where consuming and
+    /// returning `self` on every iteration would be wasteful. Adding a zero-amount coin for a
+    /// denom not already held is a no-op, preserving the invariant that the map never holds a
+    /// zero amount.
+    pub fn checked_add(&mut self, coin: &Coin) {
+        if coin.amount.is_zero() && !self.0.contains_key(&coin.denom) {
+            return;
+        }
+        let amount = self
             .0
-            .iter()
-            .enumerate()
-            .filter_map(|(i, c)| {
-                if i != 0 && c.denom == self.0[i - 1].denom {
-                    Some(i)
+            .entry(coin.denom.clone())
+            .or_insert_with(Uint128::zero);
+        *amount += coin.amount;
+    }
+
+    /// subtracts `coin` in place, taking it by reference. Leaves `self` untouched and returns an
+    /// error if the wallet doesn't hold enough of the denom: `StdError::Overflow` (carrying the
+    /// real balance and the requested amount) if the denom is present but too small, or
+    /// `StdError::NotFound` if the denom isn't held at all.
+    pub fn checked_sub(&mut self, coin: &Coin) -> StdResult<()> {
+        match self.0.get(&coin.denom) {
+            Some(&amount) => {
+                let remainder = amount
+                    .checked_sub(coin.amount)
+                    .map_err(StdError::overflow)?;
+                if remainder.is_zero() {
+                    self.0.remove(&coin.denom);
                 } else {
-                    None
+                    self.0.insert(coin.denom.clone(), remainder);
                 }
-            })
-            .collect();
-        dups.reverse();
+                Ok(())
+            }
+            None => Err(StdError::not_found(format!(
+                "balance of denom '{}'",
+                coin.denom
+            ))),
+        }
+    }
+
+    /// subtracts every coin held in `other`, in place. Validated up front the same way
+    /// `Sub<Wallet>` is, so a denom running short partway through never leaves `self`
+    /// half-decremented. Returns the same errors as `Sub<Wallet>` instead of panicking, since
+    /// insufficient balance is an ordinary runtime condition for contract code.
+    pub fn try_sub_assign(&mut self, other: Wallet) -> StdResult<()> {
+        *self = (self.clone() - other)?;
+        Ok(())
+    }
+
+    /// selects enough of `target`'s denom to cover it, returning a `Wallet` holding just that
+    /// coin, or `None` if `self` doesn't have enough. `Wallet` only ever keeps a single
+    /// aggregated amount per denom, so there's only ever one "item" to branch over here -
+    /// `branch_and_bound_select` degenerates to a plain `>=` check in that case, but is used
+    /// anyway so the all-or-nothing, no-overspend semantics stay identical to the multi-item
+    /// case callers with discrete entries (e.g. an escrow tracking each incoming `Coin`
+    /// separately) would get from calling it directly with their own `&[Uint128]`.
+    pub fn select(&self, target: &Coin) -> Option<Wallet> {
+        let available = *self.0.get(&target.denom)?;
+        branch_and_bound_select(&[available], target.amount, available)?;
+        let mut selected = Wallet::default();
+        selected.checked_add(&Coin {
+            denom: target.denom.clone(),
+            amount: available,
+        });
+        Some(selected)
+    }
 
-        // we go through the dups in reverse order (to avoid shifting indexes of other ones)
-        for dup in dups {
-            let add = self.0[dup].amount;
-            self.0[dup - 1].amount += add;
-            self.0.remove(dup);
+    /// selects enough of every denom in `targets` to cover it, combining the per-denom
+    /// selections into a single `Wallet`, or `None` if any one of them can't be covered - the
+    /// same all-or-nothing behaviour as `Sub<Wallet>`.
+    pub fn select_many(&self, targets: &[Coin]) -> Option<Wallet> {
+        let mut combined = Wallet::default();
+        for target in targets {
+            combined += self.select(target)?;
         }
+        Some(combined)
     }
+}
 
-    fn find(&self, denom: &str) -> Option<(usize, &Coin)> {
-        self.0.iter().enumerate().find(|(_i, c)| c.denom == denom)
+/// Chooses a subset of `items` whose sum covers `target` while minimizing the excess ("change")
+/// above it, via depth-first branch-and-bound search: at each item (considered sorted
+/// descending) the search branches on include/exclude, pruning any branch whose running sum
+/// already exceeds `target + cost_of_change` or that cannot possibly reach `target` even by
+/// including every remaining item. Returns the indices into `items` of the chosen subset, or
+/// `None` if no subset reaches `target` within the tolerance.
+///
+/// Public so callers that track several discrete deposits of a denom (e.g. an escrow recording
+/// each incoming `Coin` separately) can pass their own `&[Uint128]` of amounts instead of going
+/// through `Wallet`, which only ever keeps one aggregated amount per denom and so can only ever
+/// offer the degenerate single-item case via `Wallet::select`/`select_many`.
+pub fn branch_and_bound_select(
+    items: &[Uint128],
+    target: Uint128,
+    cost_of_change: Uint128,
+) -> Option<Vec<usize>> {
+    let upper_bound = target + cost_of_change;
+
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_unstable_by(|&a, &b| items[b].cmp(&items[a]));
+    let total = items.iter().fold(Uint128::zero(), |acc, &v| acc + v);
+
+    let mut selected = Vec::new();
+    let mut best: Option<(Uint128, Vec<usize>)> = None;
+
+    #[allow(clippy::too_many_arguments)]
+    fn search(
+        order: &[usize],
+        items: &[Uint128],
+        pos: usize,
+        running: Uint128,
+        remaining: Uint128,
+        target: Uint128,
+        upper_bound: Uint128,
+        selected: &mut Vec<usize>,
+        best: &mut Option<(Uint128, Vec<usize>)>,
+    ) {
+        // prune: already over the tolerance
+        if running > upper_bound {
+            return;
+        }
+        // candidate: within tolerance and an improvement on the best found so far
+        if running >= target {
+            if best.as_ref().is_none_or(|(b, _)| running < *b) {
+                *best = Some((running, selected.clone()));
+            }
+            if running == target {
+                return; // an exact match can't be improved on
+            }
+        }
+        // prune: even every remaining item can't reach target
+        if running + remaining < target {
+            return;
+        }
+        if pos == order.len() {
+            return;
+        }
+
+        let idx = order[pos];
+        let value = items[idx];
+        let rest = remaining - value;
+
+        // branch: include items[idx]
+        selected.push(idx);
+        search(
+            order,
+            items,
+            pos + 1,
+            running + value,
+            rest,
+            target,
+            upper_bound,
+            selected,
+            best,
+        );
+        selected.pop();
+
+        // branch: exclude items[idx]
+        search(
+            order,
+            items,
+            pos + 1,
+            running,
+            rest,
+            target,
+            upper_bound,
+            selected,
+            best,
+        );
     }
 
-    /// insert_pos should only be called when denom is not in the Wallet.
-    /// it returns the position where denom should be inserted at (via splice).
-    /// It returns None if this should be appended
-    fn insert_pos(&self, denom: &str) -> Option<usize> {
-        self.0.iter().position(|c| c.denom.as_str() >= denom)
+    search(
+        &order,
+        items,
+        0,
+        Uint128::zero(),
+        total,
+        target,
+        upper_bound,
+        &mut selected,
+        &mut best,
+    );
+
+    best.map(|(_, idxs)| idxs)
+}
+
+impl From<Vec<Coin>> for Wallet {
+    /// builds a Wallet from a list of coins, summing any duplicate denoms and dropping zeros
+    fn from(coins: Vec<Coin>) -> Self {
+        coins.into_iter().collect()
+    }
+}
+
+impl IntoIterator for Wallet {
+    type Item = Coin;
+    type IntoIter = std::vec::IntoIter<Coin>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl IntoIterator for &Wallet {
+    type Item = Coin;
+    type IntoIter = std::vec::IntoIter<Coin>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0
+            .iter()
+            .map(|(denom, amount)| Coin {
+                denom: denom.clone(),
+                amount: *amount,
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl std::iter::FromIterator<Coin> for Wallet {
+    /// builds a normalized Wallet from any source of coins, summing duplicate denoms
+    fn from_iter<T: IntoIterator<Item = Coin>>(iter: T) -> Self {
+        let mut wallet = Wallet::default();
+        for coin in iter {
+            wallet += coin;
+        }
+        wallet
     }
 }
 
 impl ops::AddAssign<Coin> for Wallet {
     fn add_assign(&mut self, other: Coin) {
-        match self.find(&other.denom) {
-            Some((i, c)) => {
-                self.0[i].amount = c.amount + other.amount;
-            }
-            // place this in proper sorted order
-            None => match self.insert_pos(&other.denom) {
-                Some(idx) => self.0.insert(idx, other),
-                None => self.0.push(other),
-            },
-        };
+        self.checked_add(&other);
     }
 }
 
@@ -92,8 +274,8 @@ impl ops::Add<Coin> for Wallet {
 
 impl ops::AddAssign<Wallet> for Wallet {
     fn add_assign(&mut self, other: Wallet) {
-        for coin in other.0.into_iter() {
-            self.add_assign(coin);
+        for (denom, amount) in other.0 {
+            self.checked_add(&Coin { denom, amount });
         }
     }
 }
@@ -111,31 +293,154 @@ impl ops::Sub<Coin> for Wallet {
     type Output = StdResult<Self>;
 
     fn sub(mut self, other: Coin) -> StdResult<Self> {
-        match self.find(&other.denom) {
-            Some((i, c)) => {
-                let remainder = (c.amount - other.amount)?;
-                if remainder.u128() == 0 {
-                    self.0.remove(i);
-                } else {
-                    self.0[i].amount = remainder;
+        self.checked_sub(&other)?;
+        Ok(self)
+    }
+}
+
+impl ops::Sub<Wallet> for Wallet {
+    type Output = StdResult<Self>;
+
+    /// subtracts every coin held in `other`. The whole operation is validated up front - every
+    /// denom in `other` is checked against `self`'s balance before anything is mutated - so a
+    /// denom running short partway through never leaves `self` half-decremented. Errors match
+    /// `checked_sub`/`Sub<Coin>`: `StdError::NotFound` if `self` doesn't hold the denom at all,
+    /// `StdError::Overflow` if it holds some but not enough.
+    fn sub(self, other: Wallet) -> StdResult<Self> {
+        for (denom, amount) in &other.0 {
+            match self.0.get(denom) {
+                None => {
+                    return Err(StdError::not_found(format!("balance of denom '{}'", denom)))
+                }
+                Some(&balance) if balance < *amount => {
+                    return Err(StdError::overflow(OverflowError::new(
+                        OverflowOperation::Sub,
+                        balance,
+                        *amount,
+                    )))
                 }
+                Some(_) => {}
             }
-            // error if no tokens
-            None => return StdError::underflow(0, other.amount.u128()),
-        };
-        Ok(self)
+        }
+
+        let mut result = self;
+        for (denom, amount) in other.0 {
+            result.checked_sub(&Coin { denom, amount })?;
+        }
+        Ok(result)
+    }
+}
+
+// Serialize/Deserialize go through `Vec<Coin>` so the wire format is unchanged: a plain,
+// denom-sorted JSON array of coins rather than a map.
+impl Serialize for Wallet {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.clone().into_vec().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Wallet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let coins = Vec::<Coin>::deserialize(deserializer)?;
+        Ok(Wallet::from(coins))
+    }
+}
+
+impl fmt::Display for Wallet {
+    /// writes the wallet in the canonical Cosmos coin-list format, e.g. "100ucosm,200ustake",
+    /// the same format parsed by `FromStr`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = self
+            .0
+            .iter()
+            .map(|(denom, amount)| format!("{}{}", amount, denom))
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Wallet {
+    type Err = StdError;
+
+    /// parses the canonical Cosmos coin-list format, e.g. "100ucosm,200ustake". Entries are
+    /// comma-separated; each must be a decimal amount immediately followed by a denom. An empty
+    /// string and a repeated denom are both rejected - use `Wallet::from` on a hand-built
+    /// `Vec<Coin>` if you want duplicates summed instead.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(StdError::generic_err(
+                "Parsing Wallet: cannot parse empty string",
+            ));
+        }
+
+        let mut wallet = Wallet::default();
+        // tracked separately from the map, which never holds a zero amount
+        let mut seen = std::collections::BTreeSet::new();
+        for entry in s.split(',') {
+            let coin = parse_coin(entry)?;
+            if !seen.insert(coin.denom.clone()) {
+                return Err(StdError::generic_err(format!(
+                    "Parsing Wallet: duplicate denom '{}'",
+                    coin.denom
+                )));
+            }
+            wallet += coin;
+        }
+        Ok(wallet)
+    }
+}
+
+/// parses a single `"<amount><denom>"` entry, e.g. "100ucosm"
+fn parse_coin(s: &str) -> StdResult<Coin> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| StdError::generic_err(format!("Parsing Wallet: missing denom in '{}'", s)))?;
+    if split == 0 {
+        return Err(StdError::generic_err(format!(
+            "Parsing Wallet: missing amount in '{}'",
+            s
+        )));
+    }
+    let (amount, denom) = s.split_at(split);
+    if !is_valid_denom(denom) {
+        return Err(StdError::generic_err(format!(
+            "Parsing Wallet: invalid denom '{}'",
+            denom
+        )));
     }
+    Ok(Coin {
+        denom: denom.to_string(),
+        amount: Uint128::from_str(amount)
+            .map_err(|_| StdError::generic_err(format!("Parsing Wallet: invalid amount '{}'", amount)))?,
+    })
+}
+
+/// mirrors the Cosmos SDK denom regex `[a-zA-Z][a-zA-Z0-9/:._-]{2,127}`
+fn is_valid_denom(denom: &str) -> bool {
+    if !(3..=128).contains(&denom.len()) {
+        return false;
+    }
+    let mut chars = denom.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use cosmwasm_std::{from_slice, to_vec};
+    use cosmwasm_std::{coin, from_slice, to_vec};
     use std::convert::TryInto;
 
     #[test]
     fn wallet_has_works() {
-        let wallet = Wallet(vec![coin(555, "BTC"), coin(12345, "ETH")]);
+        let wallet = Wallet::from(vec![coin(555, "BTC"), coin(12345, "ETH")]);
 
         // less than same type
         assert!(wallet.has(&coin(777, "ETH")));
@@ -150,17 +455,20 @@ mod test {
 
     #[test]
     fn wallet_add_works() {
-        let wallet = Wallet(vec![coin(555, "BTC"), coin(12345, "ETH")]);
+        let wallet = Wallet::from(vec![coin(555, "BTC"), coin(12345, "ETH")]);
 
         // add an existing coin
         let more_eth = wallet.clone() + coin(54321, "ETH");
-        assert_eq!(more_eth, Wallet(vec![coin(555, "BTC"), coin(66666, "ETH")]));
+        assert_eq!(
+            more_eth,
+            Wallet::from(vec![coin(555, "BTC"), coin(66666, "ETH")])
+        );
 
         // add an new coin
         let add_atom = wallet.clone() + coin(777, "ATOM");
         assert_eq!(
             add_atom,
-            Wallet(vec![
+            Wallet::from(vec![
                 coin(777, "ATOM"),
                 coin(555, "BTC"),
                 coin(12345, "ETH"),
@@ -170,59 +478,246 @@ mod test {
 
     #[test]
     fn wallet_in_place_addition() {
-        let mut wallet = Wallet(vec![coin(555, "BTC")]);
+        let mut wallet = Wallet::from(vec![coin(555, "BTC")]);
         wallet += coin(777, "ATOM");
-        assert_eq!(&wallet, &Wallet(vec![coin(777, "ATOM"), coin(555, "BTC")]));
+        assert_eq!(
+            &wallet,
+            &Wallet::from(vec![coin(777, "ATOM"), coin(555, "BTC")])
+        );
 
-        wallet += Wallet(vec![coin(666, "ETH"), coin(123, "ATOM")]);
+        wallet += Wallet::from(vec![coin(666, "ETH"), coin(123, "ATOM")]);
         assert_eq!(
             &wallet,
-            &Wallet(vec![coin(900, "ATOM"), coin(555, "BTC"), coin(666, "ETH")])
+            &Wallet::from(vec![coin(900, "ATOM"), coin(555, "BTC"), coin(666, "ETH")])
         );
 
-        let foo = wallet + Wallet(vec![coin(234, "BTC")]);
+        let foo = wallet + Wallet::from(vec![coin(234, "BTC")]);
         assert_eq!(
             &foo,
-            &Wallet(vec![coin(900, "ATOM"), coin(789, "BTC"), coin(666, "ETH")])
+            &Wallet::from(vec![coin(900, "ATOM"), coin(789, "BTC"), coin(666, "ETH")])
         );
     }
 
     #[test]
     fn wallet_subtract_works() {
-        let wallet = Wallet(vec![coin(555, "BTC"), coin(12345, "ETH")]);
+        let wallet = Wallet::from(vec![coin(555, "BTC"), coin(12345, "ETH")]);
 
         // subtract less than we have
         let less_eth = (wallet.clone() - coin(2345, "ETH")).unwrap();
-        assert_eq!(less_eth, Wallet(vec![coin(555, "BTC"), coin(10000, "ETH")]));
+        assert_eq!(
+            less_eth,
+            Wallet::from(vec![coin(555, "BTC"), coin(10000, "ETH")])
+        );
 
         // subtract all of one coin (and remove with 0 amount)
         let no_btc = (wallet.clone() - coin(555, "BTC")).unwrap();
-        assert_eq!(no_btc, Wallet(vec![coin(12345, "ETH")]));
-
-        // subtract more than we have
-        let underflow = wallet.clone() - coin(666, "BTC");
-        assert!(underflow.is_err());
+        assert_eq!(no_btc, Wallet::from(vec![coin(12345, "ETH")]));
+
+        // subtract more than we have: a structured overflow error carrying the real balance
+        let overflow = wallet.clone() - coin(666, "BTC");
+        match overflow.unwrap_err() {
+            StdError::Overflow { source, .. } => {
+                assert_eq!(source.operand1, "555");
+                assert_eq!(source.operand2, "666");
+            }
+            e => panic!("expected StdError::Overflow, got {:?}", e),
+        }
 
-        // subtract non-existent denom
+        // subtract a denom the wallet doesn't hold at all: a distinct not-found error
         let missing = wallet.clone() - coin(1, "ATOM");
-        assert!(missing.is_err());
+        assert!(matches!(missing.unwrap_err(), StdError::NotFound { .. }));
     }
 
     #[test]
-    fn normalize_wallet() {
-        // remove 0 value items and sort
-        let mut wallet = Wallet(vec![coin(123, "ETH"), coin(0, "BTC"), coin(8990, "ATOM")]);
-        wallet.normalize();
-        assert_eq!(wallet, Wallet(vec![coin(8990, "ATOM"), coin(123, "ETH")]));
+    fn wallet_from_vec_sorts_and_merges_duplicates() {
+        // drops 0-value entries and sorts by denom
+        let wallet = Wallet::from(vec![coin(123, "ETH"), coin(0, "BTC"), coin(8990, "ATOM")]);
+        assert_eq!(wallet, Wallet::from(vec![coin(8990, "ATOM"), coin(123, "ETH")]));
 
-        // merge duplicate entries of same denom
-        let mut wallet = Wallet(vec![
+        // sums duplicate entries of the same denom
+        let wallet = Wallet::from(vec![
             coin(123, "ETH"),
             coin(789, "BTC"),
             coin(321, "ETH"),
             coin(11, "BTC"),
         ]);
-        wallet.normalize();
-        assert_eq!(wallet, Wallet(vec![coin(800, "BTC"), coin(444, "ETH")]));
+        assert_eq!(wallet, Wallet::from(vec![coin(800, "BTC"), coin(444, "ETH")]));
+    }
+
+    #[test]
+    fn wallet_from_str_works() {
+        let wallet: Wallet = "100ucosm,200ustake".parse().unwrap();
+        assert_eq!(wallet, Wallet::from(vec![coin(100, "ucosm"), coin(200, "ustake")]));
+
+        // sorts even if the input isn't sorted
+        let wallet: Wallet = "200ustake,100ucosm".parse().unwrap();
+        assert_eq!(wallet, Wallet::from(vec![coin(100, "ucosm"), coin(200, "ustake")]));
+
+        // a single entry is fine
+        let wallet: Wallet = "555btc".parse().unwrap();
+        assert_eq!(wallet, Wallet::from(vec![coin(555, "btc")]));
+    }
+
+    #[test]
+    fn wallet_from_str_rejects_invalid_input() {
+        // empty string
+        assert!("".parse::<Wallet>().is_err());
+        // missing amount
+        assert!("ucosm".parse::<Wallet>().is_err());
+        // missing denom
+        assert!("100".parse::<Wallet>().is_err());
+        // denom too short
+        assert!("100ab".parse::<Wallet>().is_err());
+        // space between amount and denom (the parsed denom starts with a space)
+        assert!("100 1cosm".parse::<Wallet>().is_err());
+        // duplicate denom
+        assert!("100ucosm,200ucosm".parse::<Wallet>().is_err());
+    }
+
+    #[test]
+    fn wallet_display_round_trips_through_from_str() {
+        let wallet = Wallet::from(vec![coin(100, "ucosm"), coin(200, "ustake")]);
+        assert_eq!(wallet.to_string(), "100ucosm,200ustake");
+        assert_eq!(wallet.to_string().parse::<Wallet>().unwrap(), wallet);
+    }
+
+    #[test]
+    fn branch_and_bound_select_prefers_exact_match() {
+        let items = vec![
+            Uint128::new(100),
+            Uint128::new(50),
+            Uint128::new(30),
+            Uint128::new(20),
+        ];
+        // 50 + 30 + 20 == 100, an exact match beats any selection that leaves change
+        let chosen = branch_and_bound_select(&items, Uint128::new(100), Uint128::new(10)).unwrap();
+        let sum: u128 = chosen.iter().map(|&i| items[i].u128()).sum();
+        assert_eq!(sum, 100);
+    }
+
+    #[test]
+    fn branch_and_bound_select_picks_closest_above_target_within_tolerance() {
+        let items = vec![Uint128::new(90), Uint128::new(40)];
+        // neither item nor their sum hits 100 exactly; 130 is the only option within tolerance
+        let chosen = branch_and_bound_select(&items, Uint128::new(100), Uint128::new(40)).unwrap();
+        let sum: u128 = chosen.iter().map(|&i| items[i].u128()).sum();
+        assert_eq!(sum, 130);
+    }
+
+    #[test]
+    fn branch_and_bound_select_respects_cost_of_change() {
+        let items = vec![Uint128::new(200)];
+        // 200 is 100 over target, which exceeds the 10 tolerance
+        assert!(branch_and_bound_select(&items, Uint128::new(100), Uint128::new(10)).is_none());
+        // a looser tolerance accepts the same selection
+        assert!(branch_and_bound_select(&items, Uint128::new(100), Uint128::new(100)).is_some());
+    }
+
+    #[test]
+    fn branch_and_bound_select_returns_none_when_insufficient() {
+        let items = vec![Uint128::new(10), Uint128::new(20)];
+        assert!(branch_and_bound_select(&items, Uint128::new(100), Uint128::new(10)).is_none());
+    }
+
+    #[test]
+    fn wallet_select_covers_target_or_none() {
+        let wallet = Wallet::from(vec![coin(100, "ATOM")]);
+
+        assert_eq!(
+            wallet.select(&coin(40, "ATOM")),
+            Some(Wallet::from(vec![coin(100, "ATOM")]))
+        );
+        assert_eq!(wallet.select(&coin(1000, "ATOM")), None);
+        assert_eq!(wallet.select(&coin(1, "BTC")), None);
+    }
+
+    #[test]
+    fn wallet_select_many_combines_or_fails_together() {
+        let wallet = Wallet::from(vec![coin(100, "ATOM"), coin(50, "BTC")]);
+
+        assert_eq!(
+            wallet.select_many(&[coin(40, "ATOM"), coin(10, "BTC")]),
+            Some(Wallet::from(vec![coin(100, "ATOM"), coin(50, "BTC")]))
+        );
+        // fails together: BTC alone is satisfiable, but ATOM isn't, so neither is selected
+        assert_eq!(wallet.select_many(&[coin(1000, "ATOM"), coin(10, "BTC")]), None);
+    }
+
+    #[test]
+    fn checked_add_and_checked_sub_work() {
+        let mut wallet = Wallet::from(vec![coin(555, "BTC")]);
+
+        wallet.checked_add(&coin(777, "ATOM"));
+        assert_eq!(wallet, Wallet::from(vec![coin(777, "ATOM"), coin(555, "BTC")]));
+
+        wallet.checked_sub(&coin(555, "BTC")).unwrap();
+        assert_eq!(wallet, Wallet::from(vec![coin(777, "ATOM")]));
+
+        // insufficient balance errors without mutating
+        assert!(wallet.checked_sub(&coin(1000, "ATOM")).is_err());
+        assert_eq!(wallet, Wallet::from(vec![coin(777, "ATOM")]));
+    }
+
+    #[test]
+    fn wallet_bulk_subtract_works() {
+        let wallet = Wallet::from(vec![coin(555, "BTC"), coin(12345, "ETH"), coin(1, "ATOM")]);
+
+        let remainder =
+            (wallet.clone() - Wallet::from(vec![coin(55, "BTC"), coin(1, "ATOM")])).unwrap();
+        assert_eq!(remainder, Wallet::from(vec![coin(500, "BTC"), coin(12345, "ETH")]));
+
+        // insufficient balance on any one denom fails the whole operation and leaves `self`
+        // untouched (the error is returned, not applied partially)
+        let result = wallet.clone() - Wallet::from(vec![coin(1, "ATOM"), coin(999_999, "ETH")]);
+        assert!(result.is_err());
+
+        // a denom `self` doesn't hold at all surfaces the same NotFound error as
+        // checked_sub/Sub<Coin> use for the identical case
+        let result = wallet.clone() - Wallet::from(vec![coin(1, "XRP")]);
+        assert!(matches!(result.unwrap_err(), StdError::NotFound { .. }));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn wallet_try_sub_assign_works() {
+        let mut wallet = Wallet::from(vec![coin(555, "BTC"), coin(12345, "ETH")]);
+        wallet.try_sub_assign(Wallet::from(vec![coin(55, "BTC")])).unwrap();
+        assert_eq!(wallet, Wallet::from(vec![coin(500, "BTC"), coin(12345, "ETH")]));
+    }
+
+    #[test]
+    fn wallet_try_sub_assign_errors_without_mutating_on_insufficient_balance() {
+        let mut wallet = Wallet::from(vec![coin(555, "BTC")]);
+        assert!(wallet
+            .try_sub_assign(Wallet::from(vec![coin(556, "BTC")]))
+            .is_err());
+        assert_eq!(wallet, Wallet::from(vec![coin(555, "BTC")]));
+    }
+
+    #[test]
+    fn wallet_into_iter_yields_sorted_coins() {
+        let wallet = Wallet::from(vec![coin(12345, "ETH"), coin(555, "BTC")]);
+
+        let collected: Vec<Coin> = (&wallet).into_iter().collect();
+        assert_eq!(collected, vec![coin(555, "BTC"), coin(12345, "ETH")]);
+
+        // owned iteration yields the same coins and consumes the wallet
+        let collected: Vec<Coin> = wallet.into_iter().collect();
+        assert_eq!(collected, vec![coin(555, "BTC"), coin(12345, "ETH")]);
+    }
+
+    #[test]
+    fn wallet_from_iterator_merges_duplicates() {
+        let wallet: Wallet = vec![coin(123, "ETH"), coin(789, "BTC"), coin(321, "ETH")]
+            .into_iter()
+            .collect();
+        assert_eq!(wallet, Wallet::from(vec![coin(789, "BTC"), coin(444, "ETH")]));
+    }
+
+    #[test]
+    fn wallet_serializes_as_plain_coin_array() {
+        let wallet = Wallet::from(vec![coin(555, "BTC"), coin(12345, "ETH")]);
+        let serialized = to_vec(&wallet).unwrap();
+        let as_vec: Vec<Coin> = from_slice(&serialized).unwrap();
+        assert_eq!(as_vec, wallet.into_vec());
+    }
+}